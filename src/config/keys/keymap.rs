@@ -0,0 +1,256 @@
+use thiserror::Error;
+
+use super::Binding;
+use super::sequence::{self, Sequence};
+
+/// A prefix-trie over [`Binding`] sequences, e.g. for resolving `<g><g>` vs
+/// `<g>` style multi-key bindings without ambiguity. Actions are only ever
+/// stored on leaf nodes; see [`Keymap::insert`] for the conflicts this rules
+/// out.
+#[derive(Default)]
+pub struct Keymap<V> {
+    root: Vec<Sequence<V>>,
+}
+
+#[derive(Debug, Error)]
+pub enum KeymapError {
+    #[error("'{path}' cannot be bound because a shorter binding already occupies part of its path")]
+    KeyPathBlocked { path: String },
+    #[error("'{path}' is already bound")]
+    KeyAlreadySet { path: String },
+    #[error("'{path}' cannot be bound because it is a prefix of other, longer bindings")]
+    NodeHasChildren { path: String },
+}
+
+/// The result of resolving a (possibly partial) binding sequence against a
+/// [`Keymap`].
+pub enum Lookup<'a, V> {
+    /// `bindings` fully resolved to a bound action.
+    Found(&'a V),
+    /// `bindings` is a valid prefix of one or more longer bindings; more
+    /// input is needed to resolve an action.
+    Partial,
+    /// `bindings` does not match any binding, partial or otherwise.
+    NotFound,
+}
+
+impl<V> Keymap<V> {
+    /// Inserts `action` at `bindings`, walking/creating follower nodes along
+    /// the way. Fails rather than silently overwriting or shadowing an
+    /// existing binding.
+    pub fn insert(&mut self, bindings: Vec<Binding>, action: V) -> Result<(), KeymapError> {
+        let path = sequence::bindings_to_notation(&bindings);
+        Self::insert_at(&mut self.root, bindings.into_iter(), action, &path)
+    }
+
+    fn insert_at(
+        nodes: &mut Vec<Sequence<V>>,
+        mut bindings: std::vec::IntoIter<Binding>,
+        action: V,
+        path: &str,
+    ) -> Result<(), KeymapError> {
+        let Some(binding) = bindings.next() else {
+            return Ok(());
+        };
+        let is_last = bindings.len() == 0;
+
+        if let Some(node) = nodes.iter_mut().find(|node| node.binding == binding) {
+            if is_last {
+                if node.value.is_some() {
+                    return Err(KeymapError::KeyAlreadySet { path: path.to_owned() });
+                }
+                if !node.followers.is_empty() {
+                    return Err(KeymapError::NodeHasChildren { path: path.to_owned() });
+                }
+                node.value = Some(action);
+                Ok(())
+            } else if node.value.is_some() {
+                Err(KeymapError::KeyPathBlocked { path: path.to_owned() })
+            } else {
+                Self::insert_at(&mut node.followers, bindings, action, path)
+            }
+        } else if is_last {
+            let mut node = Sequence::new(binding);
+            node.value = Some(action);
+            nodes.push(node);
+            Ok(())
+        } else {
+            nodes.push(Sequence::new(binding));
+            let node = nodes.last_mut().expect("just pushed a node above");
+            Self::insert_at(&mut node.followers, bindings, action, path)
+        }
+    }
+
+    /// Resolves `bindings` against the trie. See [`Lookup`] for the possible
+    /// outcomes.
+    pub fn lookup(&self, bindings: &[Binding]) -> Lookup<'_, V> {
+        let mut nodes = &self.root;
+        let mut current = None;
+
+        for binding in bindings {
+            let Some(node) = nodes.iter().find(|node| node.binding == *binding) else {
+                return Lookup::NotFound;
+            };
+            current = Some(node);
+            nodes = &node.followers;
+        }
+
+        match current.map(|node| &node.value) {
+            Some(Some(action)) => Lookup::Found(action),
+            Some(None) => Lookup::Partial,
+            None => Lookup::NotFound,
+        }
+    }
+
+    /// Returns the children of the node reached by `bindings`, or an empty
+    /// slice if `bindings` doesn't resolve to a node at all. Used by
+    /// [`super::SequenceMatcher`] to list the valid continuations of a
+    /// pending sequence.
+    pub(crate) fn followers_at(&self, bindings: &[Binding]) -> &[Sequence<V>] {
+        let mut nodes: &[Sequence<V>] = &self.root;
+        for binding in bindings {
+            match nodes.iter().find(|node| node.binding == *binding) {
+                Some(node) => nodes = &node.followers,
+                None => return &[],
+            }
+        }
+        nodes
+    }
+
+    /// Builds a [`Keymap`] from a full set of config bindings, validating
+    /// every one up front so conflicts are reported at load time rather than
+    /// surfacing as undefined behavior at keypress time.
+    pub fn build(bindings: impl IntoIterator<Item = (Vec<Binding>, V)>) -> Result<Self, KeymapError> {
+        let mut map = Self::default();
+        for (path, action) in bindings {
+            map.insert(path, action)?;
+        }
+        Ok(map)
+    }
+
+    /// Whether any binding in this keymap needs crossterm's kitty keyboard
+    /// enhancement flags pushed to ever fire, i.e. it contains a `Media` or
+    /// `Modifier` key (see [`sequence::Parser::requires_keyboard_enhancement`]).
+    /// The caller that sets up the terminal after loading the config should
+    /// call this once and push/skip `PushKeyboardEnhancementFlags`
+    /// accordingly.
+    pub fn requires_keyboard_enhancement(&self) -> bool {
+        Self::any_requires_keyboard_enhancement(&self.root)
+    }
+
+    fn any_requires_keyboard_enhancement(nodes: &[Sequence<V>]) -> bool {
+        nodes.iter().any(|node| {
+            let binding_needs_it = match node.binding {
+                Binding::Key(key) => sequence::Parser::requires_keyboard_enhancement(&key.key),
+                Binding::Mouse(_) => false,
+            };
+            binding_needs_it || Self::any_requires_keyboard_enhancement(&node.followers)
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+    use crate::config::keys::Key;
+
+    fn key(c: char) -> Binding {
+        Binding::Key(Key { key: KeyCode::Char(c), modifiers: KeyModifiers::NONE })
+    }
+
+    #[test]
+    fn inserts_and_finds_leaf_bindings() {
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(vec![key('g'), key('g')], "go_top").unwrap();
+        map.insert(vec![key('g'), key('e')], "go_end_word").unwrap();
+
+        assert!(matches!(map.lookup(&[key('g')]), Lookup::Partial));
+        assert!(matches!(map.lookup(&[key('g'), key('g')]), Lookup::Found(&"go_top")));
+        assert!(matches!(map.lookup(&[key('g'), key('e')]), Lookup::Found(&"go_end_word")));
+        assert!(matches!(map.lookup(&[key('z')]), Lookup::NotFound));
+    }
+
+    #[test]
+    fn rejects_exact_duplicate_binding() {
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(vec![key('g')], "top").unwrap();
+
+        assert!(matches!(map.insert(vec![key('g')], "top_again"), Err(KeymapError::KeyAlreadySet { .. })));
+    }
+
+    #[test]
+    fn rejects_binding_that_shadows_a_shorter_one() {
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(vec![key('g')], "top").unwrap();
+
+        assert!(matches!(map.insert(vec![key('g'), key('g')], "go_top"), Err(KeymapError::KeyPathBlocked { .. })));
+    }
+
+    #[test]
+    fn rejects_binding_a_prefix_of_an_existing_longer_binding() {
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(vec![key('g'), key('g')], "go_top").unwrap();
+
+        assert!(matches!(map.insert(vec![key('g')], "top"), Err(KeymapError::NodeHasChildren { .. })));
+    }
+
+    #[test]
+    fn mouse_and_key_bindings_share_the_same_trie() {
+        let mut map: Keymap<&str> = Keymap::default();
+        let scroll_up = Binding::Mouse(super::super::MouseBinding {
+            kind: super::super::MouseBindingKind::ScrollUp,
+            modifiers: KeyModifiers::NONE,
+        });
+        map.insert(vec![scroll_up], "volume_up").unwrap();
+        map.insert(vec![key('g')], "top").unwrap();
+
+        assert!(matches!(map.lookup(&[scroll_up]), Lookup::Found(&"volume_up")));
+        assert!(matches!(map.lookup(&[key('g')]), Lookup::Found(&"top")));
+    }
+
+    #[test]
+    fn keymap_without_media_or_modifier_bindings_does_not_require_keyboard_enhancement() {
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(vec![key('g'), key('g')], "go_top").unwrap();
+
+        assert!(!map.requires_keyboard_enhancement());
+    }
+
+    #[test]
+    fn media_binding_requires_keyboard_enhancement() {
+        use crossterm::event::{KeyCode, MediaKeyCode};
+
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(vec![key('g')], "top").unwrap();
+        map.insert(
+            vec![Binding::Key(Key { key: KeyCode::Media(MediaKeyCode::Play), modifiers: KeyModifiers::NONE })],
+            "play",
+        )
+        .unwrap();
+
+        assert!(map.requires_keyboard_enhancement());
+    }
+
+    #[test]
+    fn modifier_binding_nested_behind_a_follower_still_requires_keyboard_enhancement() {
+        use crossterm::event::{KeyCode, ModifierKeyCode};
+
+        let mut map: Keymap<&str> = Keymap::default();
+        map.insert(
+            vec![
+                key('g'),
+                Binding::Key(Key {
+                    key: KeyCode::Modifier(ModifierKeyCode::LeftControl),
+                    modifiers: KeyModifiers::NONE,
+                }),
+            ],
+            "leader_ctrl",
+        )
+        .unwrap();
+
+        assert!(map.requires_keyboard_enhancement());
+    }
+}
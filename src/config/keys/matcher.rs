@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use super::Binding;
+use super::keymap::{Keymap, Lookup};
+use super::sequence::Sequence;
+
+/// A hint shown in a which-key-style popup: one binding that would continue
+/// the currently pending sequence, alongside its notation and, if pressing it
+/// alone would resolve to a binding, that binding's display name.
+pub struct FollowerHint {
+    pub binding: Binding,
+    pub notation: String,
+    pub action_name: Option<String>,
+}
+
+/// The result of feeding a [`Binding`] into a [`SequenceMatcher`].
+pub enum MatchState<'a, V> {
+    /// `binding` extended a valid but not yet complete sequence. The matcher
+    /// runs no timer itself: the caller arms one for
+    /// [`SequenceMatcher::idle_timeout`] and calls
+    /// [`SequenceMatcher::on_timeout`] if it elapses before the next binding
+    /// arrives.
+    Pending { followers: Vec<FollowerHint> },
+    /// `binding` completed a bound sequence.
+    Matched(&'a V),
+    /// `binding` does not continue any bound sequence; the matcher reset to
+    /// the root.
+    NoMatch,
+}
+
+/// Feeds bindings one at a time against a [`Keymap`], tracking the current
+/// partial path through its trie. After landing on an internal node the
+/// caller is expected to arm a timer
+/// for [`idle_timeout`](Self::idle_timeout) and call [`on_timeout`](Self::on_timeout)
+/// if no further binding arrives before it fires.
+pub struct SequenceMatcher<'a, V> {
+    keymap: &'a Keymap<V>,
+    idle_timeout: Duration,
+    path: Vec<Binding>,
+}
+
+impl<'a, V> SequenceMatcher<'a, V> {
+    pub fn new(keymap: &'a Keymap<V>, idle_timeout: Duration) -> Self {
+        Self { keymap, idle_timeout, path: Vec::new() }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Feeds a single binding into the matcher, extending the pending path.
+    pub fn feed(&mut self, binding: Binding) -> MatchState<'a, V>
+    where
+        V: std::fmt::Display,
+    {
+        self.path.push(binding);
+
+        match self.keymap.lookup(&self.path) {
+            Lookup::Found(action) => {
+                self.reset();
+                MatchState::Matched(action)
+            }
+            Lookup::Partial => MatchState::Pending { followers: self.followers() },
+            Lookup::NotFound => {
+                self.reset();
+                MatchState::NoMatch
+            }
+        }
+    }
+
+    /// Called by the driving event loop when [`idle_timeout`](Self::idle_timeout)
+    /// elapses without a further binding. A pending path never has a value of
+    /// its own (`Keymap::insert` rejects bindings that would give a node both
+    /// a value and followers), so there is nothing to fall back to: the
+    /// partial path is simply abandoned and the matcher resets to the root.
+    pub fn on_timeout(&mut self) -> MatchState<'a, V> {
+        self.reset();
+        MatchState::NoMatch
+    }
+
+    fn reset(&mut self) {
+        self.path.clear();
+    }
+
+    fn followers(&self) -> Vec<FollowerHint>
+    where
+        V: std::fmt::Display,
+    {
+        self.keymap
+            .followers_at(&self.path)
+            .iter()
+            .map(|node: &Sequence<V>| FollowerHint {
+                binding: node.binding,
+                notation: node.binding.to_string(),
+                action_name: node.value.as_ref().map(ToString::to_string),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+    use crate::config::keys::Key;
+
+    fn key(c: char) -> Binding {
+        Binding::Key(Key { key: KeyCode::Char(c), modifiers: KeyModifiers::NONE })
+    }
+
+    fn keymap() -> Keymap<&'static str> {
+        Keymap::build([(vec![key('g'), key('g')], "go_top"), (vec![key('g'), key('e')], "go_end_word")]).unwrap()
+    }
+
+    #[test]
+    fn matches_a_full_sequence_across_two_feeds() {
+        let map = keymap();
+        let mut matcher = SequenceMatcher::new(&map, Duration::from_millis(500));
+
+        assert!(matches!(matcher.feed(key('g')), MatchState::Pending { .. }));
+        assert!(matches!(matcher.feed(key('g')), MatchState::Matched(&"go_top")));
+    }
+
+    #[test]
+    fn pending_state_lists_the_valid_followers() {
+        let map = keymap();
+        let mut matcher = SequenceMatcher::new(&map, Duration::from_millis(500));
+
+        let MatchState::Pending { followers } = matcher.feed(key('g')) else {
+            panic!("expected a pending match");
+        };
+        assert_eq!(followers.len(), 2);
+        assert!(followers.iter().any(|f| f.binding == key('g') && f.action_name.as_deref() == Some("go_top")));
+        assert!(followers.iter().any(|f| f.binding == key('e') && f.action_name.as_deref() == Some("go_end_word")));
+    }
+
+    #[test]
+    fn unknown_key_resets_to_no_match() {
+        let map = keymap();
+        let mut matcher = SequenceMatcher::new(&map, Duration::from_millis(500));
+
+        assert!(matches!(matcher.feed(key('z')), MatchState::NoMatch));
+        assert!(matcher.path.is_empty());
+    }
+
+    #[test]
+    fn idle_timeout_abandons_a_pending_sequence() {
+        let map = keymap();
+        let mut matcher = SequenceMatcher::new(&map, Duration::from_millis(500));
+
+        matcher.feed(key('g'));
+        assert!(matches!(matcher.on_timeout(), MatchState::NoMatch));
+        assert!(matcher.path.is_empty());
+    }
+}
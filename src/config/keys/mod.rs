@@ -0,0 +1,129 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyModifiers, MediaKeyCode, ModifierKeyCode};
+
+mod keymap;
+mod matcher;
+mod mouse;
+mod sequence;
+
+pub use keymap::{Keymap, KeymapError, Lookup};
+pub use matcher::{FollowerHint, MatchState, SequenceMatcher};
+pub use mouse::{Binding, MouseBinding, MouseBindingKind};
+pub use sequence::{KeyParseError, Sequence};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub key: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Binding::from_str(s)? {
+            Binding::Key(key) => Ok(key),
+            Binding::Mouse(mouse) => anyhow::bail!("Expected a key binding, found a mouse binding: {mouse}"),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bindings = sequence::Parser::new(s)?.parse()?;
+        bindings.into_iter().next().context("Expected at least one binding in the sequence")
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_notation())
+    }
+}
+
+impl Key {
+    /// Renders this key back into its Vim-style notation, the inverse of
+    /// [`sequence::Parser::parse`] for every `KeyCode` it can actually
+    /// produce. Modifiers are emitted in the canonical `C-`/`A-`/`S-` order
+    /// and bare printable chars are left unwrapped.
+    ///
+    /// [`Key::token`]'s catch-all falls back to `Debug` for `KeyCode`
+    /// variants crossterm never hands us from parsed input (e.g.
+    /// `CapsLock`); those render as `<Foo>`-ish text that `Parser::parse`
+    /// won't recognize back.
+    pub fn to_notation(&self) -> String {
+        let (token, shift_is_implicit) = self.token();
+
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("A-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) && !shift_is_implicit {
+            prefix.push_str("S-");
+        }
+
+        if prefix.is_empty() && token.chars().count() == 1 {
+            token
+        } else {
+            format!("<{prefix}{token}>")
+        }
+    }
+
+    /// Returns the bare key name (without surrounding `<>` or modifier
+    /// prefixes) and whether that name already implies `SHIFT` on its own
+    /// (a single uppercase letter), so the caller doesn't double up `S-`.
+    fn token(&self) -> (String, bool) {
+        match self.key {
+            KeyCode::Backspace => ("BS".to_string(), false),
+            KeyCode::Enter => ("CR".to_string(), false),
+            KeyCode::Tab => ("Tab".to_string(), false),
+            KeyCode::BackTab => ("Tab".to_string(), false),
+            KeyCode::Left => ("Left".to_string(), false),
+            KeyCode::Right => ("Right".to_string(), false),
+            KeyCode::Up => ("Up".to_string(), false),
+            KeyCode::Down => ("Down".to_string(), false),
+            KeyCode::Home => ("Home".to_string(), false),
+            KeyCode::End => ("End".to_string(), false),
+            KeyCode::PageUp => ("PageUp".to_string(), false),
+            KeyCode::PageDown => ("PageDown".to_string(), false),
+            KeyCode::Delete => ("Del".to_string(), false),
+            KeyCode::Insert => ("Insert".to_string(), false),
+            KeyCode::Esc => ("Esc".to_string(), false),
+            KeyCode::Null => ("Nul".to_string(), false),
+            KeyCode::F(n) => (format!("F{n}"), false),
+            KeyCode::Char('<') => ("lt".to_string(), false),
+            KeyCode::Char('>') => ("gt".to_string(), false),
+            KeyCode::Char('|') => ("Bar".to_string(), false),
+            KeyCode::Char('\\') => ("Bslash".to_string(), false),
+            KeyCode::Char(' ') => ("Space".to_string(), false),
+            KeyCode::Char(c) if c.is_alphabetic() && c.is_uppercase() => (c.to_string(), true),
+            KeyCode::Char(c) => (c.to_string(), false),
+            KeyCode::Media(MediaKeyCode::Play) => ("MediaPlay".to_string(), false),
+            KeyCode::Media(MediaKeyCode::Pause) => ("MediaPause".to_string(), false),
+            KeyCode::Media(MediaKeyCode::PlayPause) => ("MediaPlayPause".to_string(), false),
+            KeyCode::Media(MediaKeyCode::Stop) => ("MediaStop".to_string(), false),
+            KeyCode::Media(MediaKeyCode::TrackNext) => ("MediaNext".to_string(), false),
+            KeyCode::Media(MediaKeyCode::TrackPrevious) => ("MediaPrev".to_string(), false),
+            KeyCode::Media(MediaKeyCode::RaiseVolume) => ("VolumeUp".to_string(), false),
+            KeyCode::Media(MediaKeyCode::LowerVolume) => ("VolumeDown".to_string(), false),
+            KeyCode::Media(MediaKeyCode::MuteVolume) => ("Mute".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::LeftControl) => ("LeftCtrl".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::RightControl) => ("RightCtrl".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::LeftAlt) => ("LeftAlt".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::RightAlt) => ("RightAlt".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::LeftShift) => ("LeftShift".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::RightShift) => ("RightShift".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::LeftSuper) => ("LeftSuper".to_string(), false),
+            KeyCode::Modifier(ModifierKeyCode::RightSuper) => ("RightSuper".to_string(), false),
+            other => (format!("{other:?}"), false),
+        }
+    }
+}
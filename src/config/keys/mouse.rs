@@ -0,0 +1,71 @@
+use std::fmt::Display;
+
+use crossterm::event::KeyModifiers;
+
+use super::Key;
+
+/// The kind of mouse action a [`MouseBinding`] fires on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseBindingKind {
+    ScrollUp,
+    ScrollDown,
+    LeftClick,
+    RightClick,
+    MiddleClick,
+}
+
+impl Display for MouseBindingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MouseBindingKind::ScrollUp => "ScrollUp",
+            MouseBindingKind::ScrollDown => "ScrollDown",
+            MouseBindingKind::LeftClick => "LeftClick",
+            MouseBindingKind::RightClick => "RightClick",
+            MouseBindingKind::MiddleClick => "MiddleClick",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A mouse action paired with the modifiers held while it happened, e.g.
+/// `<C-ScrollUp>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MouseBinding {
+    pub kind: MouseBindingKind,
+    pub modifiers: KeyModifiers,
+}
+
+impl Display for MouseBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("A-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            prefix.push_str("S-");
+        }
+        write!(f, "<{prefix}{}>", self.kind)
+    }
+}
+
+/// Something a [`super::Keymap`] can be indexed by: either a keyboard [`Key`]
+/// press or a [`MouseBinding`]. Lets a single keymap/matcher resolve both
+/// keyboard shortcuts and mouse gestures (e.g. scroll-over-the-volume-widget)
+/// against the same trie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseBinding),
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key}"),
+            Binding::Mouse(mouse) => write!(f, "{mouse}"),
+        }
+    }
+}
@@ -1,64 +1,159 @@
-use std::ops::RangeInclusive;
-
-use anyhow::{Result, ensure};
-use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::event::{KeyCode, KeyModifiers, MediaKeyCode, ModifierKeyCode};
 use itertools::Itertools;
+use pest::Parser as _;
+use pest_derive::Parser as PestParser;
+use thiserror::Error;
+
+use super::{Binding, Key, MouseBinding, MouseBindingKind};
+
+/// A single node of a [`super::Keymap`]'s prefix trie. Only leaf nodes (those
+/// reached by a fully bound binding path) carry a `value`; internal nodes
+/// exist purely to route to their `followers`.
+pub struct Sequence<V> {
+    pub(crate) binding: Binding,
+    pub(crate) value: Option<V>,
+    pub(crate) followers: Vec<Sequence<V>>,
+}
+
+impl<V> Sequence<V> {
+    pub(crate) fn new(binding: Binding) -> Self {
+        Self { binding, value: None, followers: Vec::new() }
+    }
+}
+
+impl<V> std::fmt::Display for Sequence<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.binding)
+    }
+}
+
+/// Renders a parsed sequence of bindings back into its Vim-style notation,
+/// the inverse of [`Parser::parse`] for every binding it can produce (see
+/// [`super::Key::to_notation`] for the one gap). Used to normalize configs
+/// and to show bound sequences in logs/help.
+pub(crate) fn bindings_to_notation<T: std::fmt::Display>(bindings: &[T]) -> String {
+    bindings.iter().map(ToString::to_string).collect()
+}
 
-use super::Key;
+/// The pest grammar for Vim-style key/mouse notation, see `keys.pest`. It
+/// only establishes where one binding ends and the next begins (and rejects
+/// things like an unterminated `<`); resolving a chord's body to a concrete
+/// [`Key`]/[`MouseBinding`] still happens in [`Parser::chord_to_binding`].
+#[derive(PestParser)]
+#[grammar = "config/keys/keys.pest"]
+struct Grammar;
+
+/// Errors produced while parsing Vim-style key/mouse notation such as
+/// `<C-S-Tab>` or `gg`.
+#[derive(Debug, Error)]
+pub enum KeyParseError {
+    #[error("Input must not be empty")]
+    EmptyInput,
+    #[error(transparent)]
+    Grammar(Box<pest::error::Error<Rule>>),
+    #[error("Unknown key name '<{name}>' at offset {pos}")]
+    UnknownKey { name: String, pos: usize },
+}
 
-pub struct Sequence {
-    key: Key,
-    followers: Vec<Sequence>,
+impl From<pest::error::Error<Rule>> for KeyParseError {
+    fn from(err: pest::error::Error<Rule>) -> Self {
+        Self::Grammar(Box::new(err))
+    }
 }
 
+type Result<T> = std::result::Result<T, KeyParseError>;
+
 #[derive(Default)]
-struct Parser<'a> {
-    idx: usize,
-    start: usize,
+pub(crate) struct Parser<'a> {
     input: &'a str,
 }
 
 impl<'a> Parser<'a> {
-    fn new(input: &'a str) -> Result<Self> {
-        ensure!(!input.is_empty(), "Input must not be empty");
+    pub(crate) fn new(input: &'a str) -> Result<Self> {
+        if input.is_empty() {
+            return Err(KeyParseError::EmptyInput);
+        }
 
-        Ok(Self { input, idx: 0, start: 0 })
+        Ok(Self { input })
     }
 
-    fn parse(&mut self) -> Result<Vec<Key>> {
-        let chars = self.input.chars().collect_vec();
-        let mut modifiers = KeyModifiers::NONE;
+    pub(crate) fn parse(&mut self) -> Result<Vec<Binding>> {
+        let sequence =
+            Grammar::parse(Rule::sequence, self.input)?.next().expect("`sequence` always produces exactly one pair");
 
         let mut seq = vec![];
-        loop {
-            if self.idx >= chars.len() {
-                break;
-            }
-            let c = chars[self.idx];
 
-            let key = match c {
-                '<' => {
-                    let chord_range = self.chord();
-                    Self::chord_to_key(&chars[chord_range])?
+        for pair in sequence.into_inner() {
+            match pair.as_rule() {
+                Rule::chord => {
+                    let pos = pair.as_span().start();
+                    let chars = pair.as_str().chars().collect_vec();
+                    seq.push(Self::chord_to_binding(&chars, pos)?);
                 }
+                Rule::plain => {
+                    let c = pair.as_str().chars().next().expect("`plain` always matches exactly one char");
+                    let modifiers = if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
 
-                c => {
-                    if c.is_uppercase() {
-                        modifiers |= KeyModifiers::SHIFT;
-                    }
-
-                    Key { key: KeyCode::Char(c), modifiers }
+                    seq.push(Binding::Key(Key { key: KeyCode::Char(c), modifiers }));
                 }
+                Rule::sequence | Rule::token | Rule::EOI => {}
+            }
+        }
+
+        Ok(seq)
+    }
+
+    /// Parses a single `<...>` chord (spanning `chars`, starting at `pos` in
+    /// the original input) into a [`Binding`], trying mouse tokens
+    /// (`<ScrollUp>`, `<C-LeftClick>`, ...) first and falling back to
+    /// [`Parser::chord_to_key`] for everything else.
+    fn chord_to_binding(chars: &[char], pos: usize) -> Result<Binding> {
+        let skip_first = &chars[1..];
+        let skip_last = &skip_first[..skip_first.len().saturating_sub(1)];
+        let mut idx = 0;
+        let mut modifiers = KeyModifiers::NONE;
+
+        loop {
+            let Some(c) = skip_last.get(idx) else {
+                break;
             };
-            seq.push(key);
 
-            self.idx += 1;
+            let next = skip_last.get(idx + 1);
+
+            match c {
+                'C' if next.is_some_and(|v| v == &'-') => {
+                    modifiers |= KeyModifiers::CONTROL;
+                    idx += 1;
+                }
+                'A' if next.is_some_and(|v| v == &'-') => {
+                    modifiers |= KeyModifiers::ALT;
+                    idx += 1;
+                }
+                'S' if next.is_some_and(|v| v == &'-') => {
+                    modifiers |= KeyModifiers::SHIFT;
+                    idx += 1;
+                }
+                _ => break,
+            }
+            idx += 1;
         }
 
-        Ok(seq)
+        let kind = match &skip_last[idx..] {
+            ['S', 'c', 'r', 'o', 'l', 'l', 'U', 'p'] => Some(MouseBindingKind::ScrollUp),
+            ['S', 'c', 'r', 'o', 'l', 'l', 'D', 'o', 'w', 'n'] => Some(MouseBindingKind::ScrollDown),
+            ['L', 'e', 'f', 't', 'C', 'l', 'i', 'c', 'k'] => Some(MouseBindingKind::LeftClick),
+            ['R', 'i', 'g', 'h', 't', 'C', 'l', 'i', 'c', 'k'] => Some(MouseBindingKind::RightClick),
+            ['M', 'i', 'd', 'd', 'l', 'e', 'C', 'l', 'i', 'c', 'k'] => Some(MouseBindingKind::MiddleClick),
+            _ => None,
+        };
+
+        match kind {
+            Some(kind) => Ok(Binding::Mouse(MouseBinding { kind, modifiers })),
+            None => Ok(Binding::Key(Self::chord_to_key(chars, pos)?)),
+        }
     }
 
-    fn chord_to_key(chars: &[char]) -> Result<Key> {
+    fn chord_to_key(chars: &[char], pos: usize) -> Result<Key> {
         let mut idx = 0;
 
         // skip the surrouning '<' and '>'
@@ -92,7 +187,7 @@ impl<'a> Parser<'a> {
         }
 
         let mut skip_last = &skip_last[idx..];
-        if skip_last[0] == '<' {
+        if skip_last.first() == Some(&'<') {
             let skip_first = &skip_last[1..];
             skip_last = &skip_first[..skip_first.len().saturating_sub(1)];
         }
@@ -121,6 +216,7 @@ impl<'a> Parser<'a> {
             ['D', 'e', 'l'] => KeyCode::Delete,
             ['I', 'n', 's', 'e', 'r', 't'] => KeyCode::Insert,
             ['E', 's', 'c'] => KeyCode::Esc,
+            ['N', 'u', 'l'] => KeyCode::Null,
             ['S', 'p', 'a', 'c', 'e'] => KeyCode::Char(' '),
             ['F', '1'] => KeyCode::F(1),
             ['F', '2'] => KeyCode::F(2),
@@ -134,44 +230,53 @@ impl<'a> Parser<'a> {
             ['F', '1', '0'] => KeyCode::F(10),
             ['F', '1', '1'] => KeyCode::F(11),
             ['F', '1', '2'] => KeyCode::F(12),
-            [] => KeyCode::Null,
-            rest @ [c, ..] => {
-                ensure!(rest.len() == 1, format!("Invalid key: '{rest:?}' from input '{chars:?}'"));
 
+            // Media keys, only reported by crossterm when the kitty keyboard
+            // protocol's enhancement flags are pushed, see `requires_keyboard_enhancement`.
+            ['M', 'e', 'd', 'i', 'a', 'P', 'l', 'a', 'y'] => KeyCode::Media(MediaKeyCode::Play),
+            ['M', 'e', 'd', 'i', 'a', 'P', 'a', 'u', 's', 'e'] => KeyCode::Media(MediaKeyCode::Pause),
+            ['M', 'e', 'd', 'i', 'a', 'P', 'l', 'a', 'y', 'P', 'a', 'u', 's', 'e'] => {
+                KeyCode::Media(MediaKeyCode::PlayPause)
+            }
+            ['M', 'e', 'd', 'i', 'a', 'S', 't', 'o', 'p'] => KeyCode::Media(MediaKeyCode::Stop),
+            ['M', 'e', 'd', 'i', 'a', 'N', 'e', 'x', 't'] => KeyCode::Media(MediaKeyCode::TrackNext),
+            ['M', 'e', 'd', 'i', 'a', 'P', 'r', 'e', 'v'] => KeyCode::Media(MediaKeyCode::TrackPrevious),
+            ['V', 'o', 'l', 'u', 'm', 'e', 'U', 'p'] => KeyCode::Media(MediaKeyCode::RaiseVolume),
+            ['V', 'o', 'l', 'u', 'm', 'e', 'D', 'o', 'w', 'n'] => KeyCode::Media(MediaKeyCode::LowerVolume),
+            ['M', 'u', 't', 'e'] => KeyCode::Media(MediaKeyCode::MuteVolume),
+
+            // Bare modifier presses, reported the same way.
+            ['L', 'e', 'f', 't', 'C', 't', 'r', 'l'] => KeyCode::Modifier(ModifierKeyCode::LeftControl),
+            ['R', 'i', 'g', 'h', 't', 'C', 't', 'r', 'l'] => KeyCode::Modifier(ModifierKeyCode::RightControl),
+            ['L', 'e', 'f', 't', 'A', 'l', 't'] => KeyCode::Modifier(ModifierKeyCode::LeftAlt),
+            ['R', 'i', 'g', 'h', 't', 'A', 'l', 't'] => KeyCode::Modifier(ModifierKeyCode::RightAlt),
+            ['L', 'e', 'f', 't', 'S', 'h', 'i', 'f', 't'] => KeyCode::Modifier(ModifierKeyCode::LeftShift),
+            ['R', 'i', 'g', 'h', 't', 'S', 'h', 'i', 'f', 't'] => KeyCode::Modifier(ModifierKeyCode::RightShift),
+            ['L', 'e', 'f', 't', 'S', 'u', 'p', 'e', 'r'] => KeyCode::Modifier(ModifierKeyCode::LeftSuper),
+            ['R', 'i', 'g', 'h', 't', 'S', 'u', 'p', 'e', 'r'] => KeyCode::Modifier(ModifierKeyCode::RightSuper),
+
+            [] => KeyCode::Null,
+            [c] => {
                 if c.is_uppercase() {
                     modifiers |= KeyModifiers::SHIFT;
                 }
 
                 KeyCode::Char(*c)
             }
+            rest => {
+                return Err(KeyParseError::UnknownKey { name: rest.iter().collect(), pos });
+            }
         };
         Ok(Key { key, modifiers })
     }
 
-    fn chord(&mut self) -> RangeInclusive<usize> {
-        let chars = self.input.chars().collect_vec();
-        let mut open_count = 1;
-        self.start = self.idx;
-        self.idx += 1;
-
-        assert!(self.idx < chars.len(), "unterminated chord");
-        let mut current_char = chars[self.idx]; // possible panic
-
-        loop {
-            self.idx += 1;
-
-            current_char = chars[self.idx];
-            if current_char == '>' {
-                open_count -= 1;
-                if open_count == 0 {
-                    break;
-                }
-            } else if current_char == '<' {
-                open_count += 1;
-            }
-        }
-
-        self.start..=self.idx
+    /// Whether recognizing `key` at all requires the terminal's kitty keyboard
+    /// enhancement protocol to be enabled. crossterm only ever reports
+    /// `KeyCode::Media`/`KeyCode::Modifier` events when `PushKeyboardEnhancementFlags`
+    /// was sent, so the caller driving the terminal setup uses this to decide
+    /// whether any bound key requires pushing those flags.
+    pub(crate) fn requires_keyboard_enhancement(key: &KeyCode) -> bool {
+        matches!(key, KeyCode::Media(_) | KeyCode::Modifier(_))
     }
 }
 
@@ -203,6 +308,24 @@ mod tests {
         dbg!(Parser::new("<lt>").unwrap().parse());
     }
 
+    #[test]
+    fn empty_input_is_an_error_not_a_panic() {
+        assert!(matches!(Parser::new(""), Err(KeyParseError::EmptyInput)));
+    }
+
+    #[test]
+    fn unterminated_chord_is_an_error_not_a_panic() {
+        assert!(matches!(Parser::new("<C-").unwrap().parse(), Err(KeyParseError::Grammar(_))));
+    }
+
+    #[test]
+    fn unknown_key_name_is_an_error_not_a_panic() {
+        assert!(matches!(
+            Parser::new("<Foobar>").unwrap().parse(),
+            Err(KeyParseError::UnknownKey { name, .. }) if name == "Foobar"
+        ));
+    }
+
     #[rstest]
     //      <BS>		              backspace
     #[case("<BS>",         Key { key: KeyCode::Backspace, modifiers: KeyModifiers::NONE })]
@@ -309,6 +432,21 @@ mod tests {
     #[case("5",            Key { key: KeyCode::Char('5'), modifiers: KeyModifiers::NONE })]
     #[case("<C-A-S-5>",    Key { key: KeyCode::Char('5'), modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT | KeyModifiers::ALT })]
 
+    //      <MediaPlay> and friends        MPD transport / media keys
+    #[case("<MediaPlay>",      Key { key: KeyCode::Media(MediaKeyCode::Play),          modifiers: KeyModifiers::NONE })]
+    #[case("<MediaPause>",     Key { key: KeyCode::Media(MediaKeyCode::Pause),         modifiers: KeyModifiers::NONE })]
+    #[case("<MediaPlayPause>", Key { key: KeyCode::Media(MediaKeyCode::PlayPause),     modifiers: KeyModifiers::NONE })]
+    #[case("<MediaStop>",      Key { key: KeyCode::Media(MediaKeyCode::Stop),          modifiers: KeyModifiers::NONE })]
+    #[case("<MediaNext>",      Key { key: KeyCode::Media(MediaKeyCode::TrackNext),     modifiers: KeyModifiers::NONE })]
+    #[case("<MediaPrev>",      Key { key: KeyCode::Media(MediaKeyCode::TrackPrevious), modifiers: KeyModifiers::NONE })]
+    #[case("<VolumeUp>",       Key { key: KeyCode::Media(MediaKeyCode::RaiseVolume),   modifiers: KeyModifiers::NONE })]
+    #[case("<VolumeDown>",     Key { key: KeyCode::Media(MediaKeyCode::LowerVolume),   modifiers: KeyModifiers::NONE })]
+    #[case("<Mute>",           Key { key: KeyCode::Media(MediaKeyCode::MuteVolume),    modifiers: KeyModifiers::NONE })]
+
+    //      <LeftCtrl>, <RightAlt>, ...     bare modifier-key presses
+    #[case("<LeftCtrl>",   Key { key: KeyCode::Modifier(ModifierKeyCode::LeftControl),  modifiers: KeyModifiers::NONE })]
+    #[case("<RightAlt>",   Key { key: KeyCode::Modifier(ModifierKeyCode::RightAlt),     modifiers: KeyModifiers::NONE })]
+
     #[case("_",            Key { key: KeyCode::Char('_'), modifiers: KeyModifiers::NONE })]
     #[case("-",            Key { key: KeyCode::Char('-'), modifiers: KeyModifiers::NONE })]
     #[case("<C-S-->",      Key { key: KeyCode::Char('-'), modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT })]
@@ -319,7 +457,7 @@ mod tests {
         println!("input {input}");
         let deserialized = Parser::new(input).unwrap().parse().unwrap();
         // let deserialized: Key = input.parse().unwrap();
-        assert_eq!(deserialized[0], expected);
+        assert_eq!(deserialized[0], Binding::Key(expected));
     }
 
     #[rstest]
@@ -329,4 +467,65 @@ mod tests {
         let deserialized: Key = input.parse().unwrap();
         assert_eq!(deserialized, expected);
     }
+
+    #[rstest]
+    #[case(Key { key: KeyCode::Char('a'), modifiers: KeyModifiers::NONE })]
+    #[case(Key { key: KeyCode::Char('A'), modifiers: KeyModifiers::SHIFT })]
+    #[case(Key { key: KeyCode::Backspace, modifiers: KeyModifiers::CONTROL })]
+    #[case(Key { key: KeyCode::Tab, modifiers: KeyModifiers::CONTROL })]
+    #[case(Key { key: KeyCode::BackTab, modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT })]
+    #[case(Key { key: KeyCode::Enter, modifiers: KeyModifiers::NONE })]
+    #[case(Key { key: KeyCode::F(11), modifiers: KeyModifiers::SHIFT })]
+    #[case(Key { key: KeyCode::Char('<'), modifiers: KeyModifiers::NONE })]
+    #[case(Key { key: KeyCode::Char('|'), modifiers: KeyModifiers::SHIFT })]
+    #[case(Key { key: KeyCode::Char(' '), modifiers: KeyModifiers::CONTROL })]
+    #[case(Key { key: KeyCode::Char('\\'), modifiers: KeyModifiers::NONE })]
+    #[case(Key { key: KeyCode::Null, modifiers: KeyModifiers::NONE })]
+    fn notation_round_trip(#[case] key: Key) {
+        let notation = key.to_notation();
+        let reparsed = Parser::new(&notation).unwrap().parse().unwrap();
+        assert_eq!(reparsed[0], Binding::Key(key));
+    }
+
+    #[test]
+    fn notation_sequence_round_trip() {
+        let bindings = Parser::new("<C-t><S-w>").unwrap().parse().unwrap();
+        let notation = bindings_to_notation(&bindings);
+        assert_eq!(notation, "<C-t><S-w>");
+
+        let reparsed = Parser::new(&notation).unwrap().parse().unwrap();
+        assert_eq!(reparsed, bindings);
+    }
+
+    #[test]
+    fn plain_chars_in_a_sequence_do_not_leak_shift_across_each_other() {
+        let bindings = Parser::new("Gg").unwrap().parse().unwrap();
+        assert_eq!(bindings, vec![
+            Binding::Key(Key { key: KeyCode::Char('G'), modifiers: KeyModifiers::SHIFT }),
+            Binding::Key(Key { key: KeyCode::Char('g'), modifiers: KeyModifiers::NONE }),
+        ]);
+    }
+
+    #[rstest]
+    #[case("<ScrollUp>",      MouseBinding { kind: MouseBindingKind::ScrollUp,   modifiers: KeyModifiers::NONE })]
+    #[case("<ScrollDown>",    MouseBinding { kind: MouseBindingKind::ScrollDown, modifiers: KeyModifiers::NONE })]
+    #[case("<LeftClick>",     MouseBinding { kind: MouseBindingKind::LeftClick,  modifiers: KeyModifiers::NONE })]
+    #[case("<RightClick>",    MouseBinding { kind: MouseBindingKind::RightClick, modifiers: KeyModifiers::NONE })]
+    #[case("<MiddleClick>",   MouseBinding { kind: MouseBindingKind::MiddleClick,modifiers: KeyModifiers::NONE })]
+    #[case("<C-ScrollUp>",    MouseBinding { kind: MouseBindingKind::ScrollUp,   modifiers: KeyModifiers::CONTROL })]
+    #[case("<C-S-ScrollDown>",MouseBinding { kind: MouseBindingKind::ScrollDown,modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT })]
+    fn mouse_binding_parsing(#[case] input: &str, #[case] expected: MouseBinding) {
+        let parsed = Parser::new(input).unwrap().parse().unwrap();
+        assert_eq!(parsed[0], Binding::Mouse(expected));
+    }
+
+    #[test]
+    fn mouse_binding_notation_round_trip() {
+        let bindings = Parser::new("<C-ScrollUp>").unwrap().parse().unwrap();
+        let notation = bindings_to_notation(&bindings);
+        assert_eq!(notation, "<C-ScrollUp>");
+
+        let reparsed = Parser::new(&notation).unwrap().parse().unwrap();
+        assert_eq!(reparsed, bindings);
+    }
 }